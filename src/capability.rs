@@ -0,0 +1,205 @@
+//! Support for walking a function's capability list, which is a linked list of structures living
+//! in its configuration space, rooted at the Capabilities Pointer (offset `0x34` of a Type-0 or
+//! Type-1 header).
+
+use crate::{ConfigRegionAccess, PciAddress};
+use bit_field::BitField;
+
+/// A single entry in a function's capability list. Each capability is identified by an 8-bit ID;
+/// unrecognised IDs are preserved as `Unknown` so that callers can still walk past them.
+#[derive(Clone, Copy, Debug)]
+pub enum PciCapability {
+    Msi(MsiCapability),
+    Msix(MsixCapability),
+    Vendor(u8),
+    Unknown { id: u8, offset: u8 },
+}
+
+/// The decoded contents of an MSI (Message Signalled Interrupts) capability structure. The
+/// Message Address (and, for 64-bit capable functions, Message Upper Address) and Message Data
+/// registers live immediately after the capability header, but at offsets that depend on the
+/// `is_64bit_capable` and `per_vector_masking_capable` bits, so this exposes their offsets rather
+/// than fixed constants.
+#[derive(Clone, Copy, Debug)]
+pub struct MsiCapability {
+    offset: u8,
+    enabled: bool,
+    multiple_message_capable: u8,
+    multiple_message_enable: u8,
+    is_64bit_capable: bool,
+    per_vector_masking_capable: bool,
+}
+
+impl MsiCapability {
+    /// Offset into this function's configuration space that this capability starts at.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The number of vectors the function is capable of requesting, encoded as a 3-bit log2 (e.g.
+    /// `2` means the function can request up to `2^2 = 4` vectors).
+    pub fn multiple_message_capable(&self) -> u8 {
+        self.multiple_message_capable
+    }
+
+    /// The number of vectors the system has allocated to the function, encoded the same way as
+    /// `multiple_message_capable`.
+    pub fn multiple_message_enable(&self) -> u8 {
+        self.multiple_message_enable
+    }
+
+    pub fn is_64bit_capable(&self) -> bool {
+        self.is_64bit_capable
+    }
+
+    pub fn per_vector_masking_capable(&self) -> bool {
+        self.per_vector_masking_capable
+    }
+
+    /// Offset of the 32-bit Message Address register.
+    pub fn message_address_offset(&self) -> u8 {
+        self.offset + 0x04
+    }
+
+    /// Offset of the 32-bit Message Upper Address register, if this capability is 64-bit capable.
+    pub fn message_upper_address_offset(&self) -> Option<u8> {
+        self.is_64bit_capable.then(|| self.offset + 0x08)
+    }
+
+    /// Offset of the 16-bit Message Data register.
+    pub fn message_data_offset(&self) -> u8 {
+        self.offset + if self.is_64bit_capable { 0x0c } else { 0x08 }
+    }
+
+    /// Offset of the 32-bit Mask Bits register, if this capability supports per-vector masking.
+    pub fn mask_bits_offset(&self) -> Option<u8> {
+        self.per_vector_masking_capable
+            .then(|| self.offset + if self.is_64bit_capable { 0x10 } else { 0x0c })
+    }
+
+    /// Offset of the 32-bit Pending Bits register, if this capability supports per-vector
+    /// masking.
+    pub fn pending_bits_offset(&self) -> Option<u8> {
+        self.per_vector_masking_capable
+            .then(|| self.offset + if self.is_64bit_capable { 0x14 } else { 0x10 })
+    }
+}
+
+/// The decoded contents of an MSI-X capability structure.
+#[derive(Clone, Copy, Debug)]
+pub struct MsixCapability {
+    enabled: bool,
+    function_mask: bool,
+    table_size: u16,
+    table_bar: u8,
+    table_offset: u32,
+    pba_bar: u8,
+    pba_offset: u32,
+}
+
+impl MsixCapability {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn function_mask(&self) -> bool {
+        self.function_mask
+    }
+
+    /// The number of entries in the MSI-X table, which is one more than the 11-bit field stored
+    /// in the capability.
+    pub fn table_size(&self) -> u16 {
+        self.table_size + 1
+    }
+
+    /// The index of the BAR that the MSI-X table lives in, and the table's byte offset into that
+    /// BAR.
+    pub fn table_bar_and_offset(&self) -> (u8, u32) {
+        (self.table_bar, self.table_offset)
+    }
+
+    /// The index of the BAR that the Pending Bit Array lives in, and its byte offset into that
+    /// BAR.
+    pub fn pba_bar_and_offset(&self) -> (u8, u32) {
+        (self.pba_bar, self.pba_offset)
+    }
+}
+
+/// Walks a function's capability list, yielding each entry in turn. Constructed by
+/// [`EndpointHeader::capabilities`](crate::EndpointHeader::capabilities).
+pub struct CapabilityIterator<'a, T>
+where
+    T: ConfigRegionAccess,
+{
+    address: PciAddress,
+    access: &'a T,
+    next_pointer: u16,
+}
+
+impl<'a, T> CapabilityIterator<'a, T>
+where
+    T: ConfigRegionAccess,
+{
+    pub(crate) fn new(address: PciAddress, pointer: u16, access: &'a T) -> Self {
+        Self {
+            address,
+            access,
+            next_pointer: pointer,
+        }
+    }
+}
+
+impl<'a, T> Iterator for CapabilityIterator<'a, T>
+where
+    T: ConfigRegionAccess,
+{
+    type Item = PciCapability;
+
+    fn next(&mut self) -> Option<PciCapability> {
+        /*
+         * The capability list is terminated by a null pointer.
+         */
+        if self.next_pointer == 0x00 {
+            return None;
+        }
+
+        let offset = self.next_pointer;
+        let dword = self.access.read(self.address, offset);
+        let id = dword.get_bits(0..8) as u8;
+        self.next_pointer = dword.get_bits(8..16) as u16;
+        let control = dword.get_bits(16..32) as u16;
+
+        match id {
+            0x05 => Some(PciCapability::Msi(MsiCapability {
+                offset: offset as u8,
+                enabled: control.get_bit(0),
+                multiple_message_capable: control.get_bits(1..4) as u8,
+                multiple_message_enable: control.get_bits(4..7) as u8,
+                is_64bit_capable: control.get_bit(7),
+                per_vector_masking_capable: control.get_bit(8),
+            })),
+            0x09 => Some(PciCapability::Vendor(offset as u8)),
+            0x11 => {
+                let table = self.access.read(self.address, offset + 0x04);
+                let pba = self.access.read(self.address, offset + 0x08);
+                Some(PciCapability::Msix(MsixCapability {
+                    enabled: control.get_bit(15),
+                    function_mask: control.get_bit(14),
+                    table_size: control.get_bits(0..11),
+                    table_bar: table.get_bits(0..3) as u8,
+                    table_offset: table.get_bits(3..32) << 3,
+                    pba_bar: pba.get_bits(0..3) as u8,
+                    pba_offset: pba.get_bits(3..32) << 3,
+                }))
+            }
+            _ => Some(PciCapability::Unknown {
+                id,
+                offset: offset as u8,
+            }),
+        }
+    }
+}
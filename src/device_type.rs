@@ -0,0 +1,70 @@
+//! Interprets the (base class, sub class, interface) triple read from a function's predefined
+//! header into a friendlier representation of what kind of device it is.
+
+use crate::{BaseClass, Interface, SubClass};
+
+/// A coarse classification of what a PCI function actually does, decoded from its class code
+/// registers. Unrecognised combinations are preserved as `Unknown` with the raw codes so callers
+/// can still make decisions about them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeviceType {
+    Unclassified,
+    MassStorageController,
+    NetworkController,
+    DisplayController,
+    MultimediaController,
+    MemoryController,
+    BridgeDevice,
+    SimpleCommunicationController,
+    BaseSystemPeripheral,
+    InputDeviceController,
+    DockingStation,
+    Processor,
+    SerialBusController,
+    WirelessController,
+    IntelligentController,
+    SatelliteCommunicationController,
+    EncryptionController,
+    SignalProcessingController,
+    ProcessingAccelerator,
+    NonEssentialInstrumentation,
+    CoProcessor,
+    Unknown {
+        base_class: BaseClass,
+        sub_class: SubClass,
+        interface: Interface,
+    },
+}
+
+impl DeviceType {
+    pub fn new(base_class: BaseClass, sub_class: SubClass, interface: Interface) -> DeviceType {
+        match base_class {
+            0x01 => DeviceType::MassStorageController,
+            0x02 => DeviceType::NetworkController,
+            0x03 => DeviceType::DisplayController,
+            0x04 => DeviceType::MultimediaController,
+            0x05 => DeviceType::MemoryController,
+            0x06 => DeviceType::BridgeDevice,
+            0x07 => DeviceType::SimpleCommunicationController,
+            0x08 => DeviceType::BaseSystemPeripheral,
+            0x09 => DeviceType::InputDeviceController,
+            0x0a => DeviceType::DockingStation,
+            0x0b => DeviceType::Processor,
+            0x0c => DeviceType::SerialBusController,
+            0x0d => DeviceType::WirelessController,
+            0x0e => DeviceType::IntelligentController,
+            0x0f => DeviceType::SatelliteCommunicationController,
+            0x10 => DeviceType::EncryptionController,
+            0x11 => DeviceType::SignalProcessingController,
+            0x12 => DeviceType::ProcessingAccelerator,
+            0x13 => DeviceType::NonEssentialInstrumentation,
+            0x40 => DeviceType::CoProcessor,
+            0x00 if sub_class == 0x00 => DeviceType::Unclassified,
+            _ => DeviceType::Unknown {
+                base_class,
+                sub_class,
+                interface,
+            },
+        }
+    }
+}
@@ -4,7 +4,7 @@ pub mod capability;
 pub mod device_type;
 mod register;
 
-pub use register::{DevselTiming, StatusRegister};
+pub use register::{CommandRegister, DevselTiming, StatusRegister};
 
 use crate::capability::CapabilityIterator;
 use bit_field::BitField;
@@ -99,6 +99,8 @@ pub type BaseClass = u8;
 pub type SubClass = u8;
 pub type Interface = u8;
 pub type HeaderType = u8;
+pub type SubsystemVendorId = u16;
+pub type SubsystemId = u16;
 
 // TODO: documentation
 pub trait ConfigRegionAccess: Send {
@@ -179,6 +181,19 @@ impl PciHeader {
         let data = access.read(self.0, 0x4).get_bits(16..32);
         StatusRegister::new(data as u16)
     }
+
+    pub fn command(&self, access: &impl ConfigRegionAccess) -> CommandRegister {
+        let data = access.read(self.0, 0x4).get_bits(0..16);
+        CommandRegister::new(data as u16)
+    }
+
+    /// Write `command` back to the Command register at offset `0x04`. The Status half of the
+    /// dword is written as all-zero rather than read back first: most of its bits are
+    /// write-1-to-clear, so echoing back whatever was last read would silently acknowledge any
+    /// pending status conditions (e.g. a parity or system error) as a side effect.
+    pub fn set_command(&self, access: &impl ConfigRegionAccess, command: CommandRegister) {
+        access.write(self.0, 0x4, command.as_u16() as u32);
+    }
 }
 
 /// Endpoints have a Type-0 header, so the remainder of the header is of the form:
@@ -270,7 +285,8 @@ impl EndpointHeader {
     ///
     /// ### Note
     /// 64-bit memory BARs use two slots, so if one is decoded in e.g. slot #0, this method should not be called
-    /// for slot #1
+    /// for slot #1. Prefer [`EndpointHeader::bars`] if you want to decode every slot, as it skips the upper half
+    /// of 64-bit BARs automatically.
     pub fn bar(&self, slot: u8, access: &impl ConfigRegionAccess) -> Option<Bar> {
         let offset = 0x10 + (slot as u16) * 4;
         let bar = access.read(self.0, offset);
@@ -278,43 +294,65 @@ impl EndpointHeader {
         /*
          * If bit 0 is `0`, the BAR is in memory. If it's `1`, it's in I/O.
          */
-        if bar.get_bit(0) == false {
+        if !bar.get_bit(0) {
             let prefetchable = bar.get_bit(3);
             let address = bar.get_bits(4..32) << 4;
 
-            // TODO: if the bar is 64-bits, do we need to do this on both BARs?
-            let size = {
-                access.write(self.0, offset, 0xffffffff);
-                let mut readback = access.read(self.0, offset);
-                access.write(self.0, offset, address);
-
-                /*
-                 * If the entire readback value is zero, the BAR is not implemented, so we return `None`.
-                 */
-                if readback == 0x0 {
-                    return None;
-                }
-
-                readback.set_bits(0..4, 0);
-                1 << readback.trailing_zeros()
-            };
-
             match bar.get_bits(1..3) {
-                0b00 => Some(Bar::Memory32 {
-                    address,
-                    size,
-                    prefetchable,
-                }),
+                0b00 => {
+                    let size = {
+                        access.write(self.0, offset, 0xffffffff);
+                        let mut readback = access.read(self.0, offset);
+                        access.write(self.0, offset, bar);
+
+                        /*
+                         * If the entire readback value is zero, the BAR is not implemented, so we return `None`.
+                         */
+                        if readback == 0x0 {
+                            return None;
+                        }
+
+                        readback.set_bits(0..4, 0);
+                        1 << readback.trailing_zeros()
+                    };
+
+                    Some(Bar::Memory32 {
+                        address,
+                        size,
+                        prefetchable,
+                    })
+                }
                 0b10 => {
-                    let address = {
-                        let mut address = address as u64;
-                        // TODO: do we need to mask off the lower bits on this?
-                        address.set_bits(32..64, access.read(self.0, offset + 4) as u64);
-                        address
+                    let bar_high = access.read(self.0, offset + 4);
+                    let mut address = address as u64;
+                    address.set_bits(32..64, bar_high as u64);
+
+                    /*
+                     * A 64-bit BAR's size can be larger than 4 GiB, so we have to size it by probing both the
+                     * low and high dwords with all-ones and reconstructing the full 64-bit mask, rather than
+                     * just the low one.
+                     */
+                    let size = {
+                        access.write(self.0, offset, 0xffffffff);
+                        access.write(self.0, offset + 4, 0xffffffff);
+                        let mut readback_low = access.read(self.0, offset);
+                        let readback_high = access.read(self.0, offset + 4);
+                        access.write(self.0, offset, bar);
+                        access.write(self.0, offset + 4, bar_high);
+
+                        if readback_low == 0x0 && readback_high == 0x0 {
+                            return None;
+                        }
+
+                        readback_low.set_bits(0..4, 0);
+                        let mut mask = readback_low as u64;
+                        mask.set_bits(32..64, readback_high as u64);
+                        (!mask).wrapping_add(1)
                     };
+
                     Some(Bar::Memory64 {
                         address,
-                        size: size as u64,
+                        size,
                         prefetchable,
                     })
                 }
@@ -327,6 +365,91 @@ impl EndpointHeader {
             })
         }
     }
+
+    /// Decode every BAR slot in one pass, automatically skipping the upper half of any 64-bit
+    /// BAR so callers don't have to reason about slot aliasing themselves.
+    pub fn bars(&self, access: &impl ConfigRegionAccess) -> [Option<Bar>; MAX_BARS] {
+        let mut bars = [None; MAX_BARS];
+        let mut slot = 0;
+
+        while (slot as usize) < MAX_BARS {
+            match self.bar(slot, access) {
+                Some(bar @ Bar::Memory64 { .. }) => {
+                    bars[slot as usize] = Some(bar);
+                    slot += 2;
+                }
+                bar => {
+                    bars[slot as usize] = bar;
+                    slot += 1;
+                }
+            }
+        }
+
+        bars
+    }
+
+    /// Get the contents of the Expansion ROM Base Address register at offset `0x30`. crosvm and
+    /// cloud-hypervisor treat this as a seventh BAR (`ROM_BAR_IDX = 6`), since it's sized and
+    /// decoded in exactly the same way as a 32-bit memory BAR, just with a minimum size of 2048
+    /// bytes and an explicit enable bit instead of a memory-space-type field.
+    pub fn expansion_rom_bar(&self, access: &impl ConfigRegionAccess) -> Option<ExpansionRom> {
+        let offset = 0x30;
+        let rom = access.read(self.0, offset);
+
+        let enabled = rom.get_bit(0);
+        let address = rom.get_bits(11..32) << 11;
+
+        let size = {
+            access.write(self.0, offset, 0xffff_f800 | (enabled as u32));
+            let mut readback = access.read(self.0, offset);
+            access.write(self.0, offset, rom);
+
+            if readback == 0x0 {
+                return None;
+            }
+
+            readback.set_bits(0..11, 0);
+            1 << readback.trailing_zeros()
+        };
+
+        Some(ExpansionRom {
+            address,
+            size,
+            enabled,
+        })
+    }
+
+    /// Get the Interrupt Line and Interrupt Pin fields at offset `0x3c`, used for legacy INTx
+    /// routing. The line is software-assigned; the pin is read-only and tells the OS which of the
+    /// four `INTA#`-`INTD#` pins this function uses, or `0` if it doesn't use interrupts.
+    pub fn interrupt(&self, access: &impl ConfigRegionAccess) -> (u8, u8) {
+        let data = access.read(self.0, 0x3c);
+        (data.get_bits(0..8) as u8, data.get_bits(8..16) as u8)
+    }
+
+    /// Set the Interrupt Line field at offset `0x3c`. This is the only part of that register
+    /// software is permitted to write; the Interrupt Pin is read-only.
+    pub fn set_interrupt_line(&self, access: &impl ConfigRegionAccess, line: u8) {
+        let mut data = access.read(self.0, 0x3c);
+        data.set_bits(0..8, line as u32);
+        access.write(self.0, 0x3c, data);
+    }
+
+    pub fn subsystem(&self, access: &impl ConfigRegionAccess) -> (SubsystemVendorId, SubsystemId) {
+        let data = access.read(self.0, 0x2c);
+        (
+            data.get_bits(0..16) as SubsystemVendorId,
+            data.get_bits(16..32) as SubsystemId,
+        )
+    }
+}
+
+/// The decoded contents of an Expansion ROM Base Address register.
+#[derive(Clone, Copy, Debug)]
+pub struct ExpansionRom {
+    pub address: u32,
+    pub size: u32,
+    pub enabled: bool,
 }
 
 pub const MAX_BARS: usize = 6;
@@ -347,3 +470,240 @@ pub enum Bar {
         port: u32,
     },
 }
+
+/// The result of [`BarInfo::handle_write`]: a write to a BAR's dword that was not a sizing probe,
+/// and so relocated the BAR's base address. A VMM backing this BAR with an MMIO mapping should
+/// move that mapping from `old_address` to `new_address`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarReprogramming {
+    pub bar_index: u8,
+    pub old_address: u64,
+    pub new_address: u64,
+    pub size: u64,
+}
+
+/// Tracks the address and size-mask bits of a single memory BAR, as discovered once via
+/// [`EndpointHeader::bar`], so that subsequent writes an emulated function receives on that BAR's
+/// dword(s) can be told apart from a guest driver's sizing probe.
+///
+/// The naive rule "a write of `0xffffffff` means the guest wants the size" is wrong for 64-bit
+/// BARs, because `0xffffffff` may legitimately be the high or low dword of a real address. Instead,
+/// `address_mask` (derived once from the BAR's size, the same way [`EndpointHeader::bar`] derives
+/// it) records which address bits this BAR actually decodes, and a write only counts as a sizing
+/// probe if all of those bits in the dword being written are ones while the other dword of a
+/// 64-bit pair is left unchanged (see [`BarInfo::handle_write`]).
+#[derive(Clone, Copy, Debug)]
+pub struct BarInfo {
+    bar_index: u8,
+    is_64bit: bool,
+    size: u64,
+    /// Mask of the address bits this BAR decodes (bits below the size, and the low four
+    /// type/prefetch bits of the low dword, are excluded). For a 32-bit BAR, only the low 32 bits
+    /// of the mask are meaningful.
+    address_mask: u64,
+    low: u32,
+    high: u32,
+}
+
+impl BarInfo {
+    /// Start tracking a BAR slot, given the value originally decoded by `EndpointHeader::bar` (or
+    /// `EndpointHeader::bars`). Returns `None` for I/O BARs, which this crate doesn't size.
+    pub fn new(bar_index: u8, bar: Bar) -> Option<BarInfo> {
+        match bar {
+            Bar::Memory32 { address, size, .. } => Some(BarInfo {
+                bar_index,
+                is_64bit: false,
+                size: size as u64,
+                address_mask: !((size as u64) - 1) & 0xffff_ffff & !0xf,
+                low: address,
+                high: 0,
+            }),
+            Bar::Memory64 { address, size, .. } => Some(BarInfo {
+                bar_index,
+                is_64bit: true,
+                size,
+                address_mask: !(size - 1) & !0xf,
+                low: address as u32,
+                high: address.get_bits(32..64) as u32,
+            }),
+            Bar::Io { .. } => None,
+        }
+    }
+
+    /// Classify a write of `value` to this BAR's low dword (`high_dword = false`) or, for a
+    /// 64-bit BAR, its high dword (`high_dword = true`). Returns `Some` if this was a real
+    /// reprogramming of the base address, or `None` if it was a sizing probe.
+    ///
+    /// ### Note
+    /// A 64-bit BAR's two dwords are sized one at a time: the guest (Linux/SeaBIOS/OVMF) writes
+    /// `0xffff_ffff` to the low dword while the high dword still holds the real upper address,
+    /// reads the size back, and restores the low dword before ever touching the high dword. So a
+    /// sizing probe is recognised per-dword — the written dword's decoded address bits go all-ones
+    /// while the *other*, not-yet-written dword is left unchanged — rather than by requiring both
+    /// dwords to read back all-ones at once. This method only reports a [`BarReprogramming`] from a
+    /// low-dword write; a high-dword write only updates the latched state, since reporting there
+    /// too would surface a bogus intermediate address built from the new high half and the
+    /// still-stale low half.
+    pub fn handle_write(&mut self, high_dword: bool, value: u32) -> Option<BarReprogramming> {
+        assert!(!high_dword || self.is_64bit, "only a 64-bit BAR has a high dword");
+
+        let old_low = self.low;
+        let old_high = self.high;
+        let (new_low, new_high) = if high_dword { (old_low, value) } else { (value, old_high) };
+
+        let low_mask = self.address_mask as u32;
+        let high_mask = (self.address_mask >> 32) as u32;
+
+        let is_size_probe = if high_dword {
+            (new_high & high_mask) == high_mask && new_low == self.low
+        } else {
+            (new_low & low_mask) == low_mask && new_high == self.high
+        };
+
+        if is_size_probe {
+            // Sizing probe: the dword being written has gone all-ones (within the bits this BAR
+            // actually decodes) while the other dword is untouched. Leave the pre-probe value
+            // uncommitted; the guest will shortly restore the real address, and that restoring
+            // write is what gets reported as a reprogramming below.
+            return None;
+        }
+
+        self.low = new_low;
+        self.high = new_high;
+
+        if high_dword {
+            return None;
+        }
+
+        let mut old_address = old_low as u64 & !0xf;
+        let mut new_address = new_low as u64 & !0xf;
+        if self.is_64bit {
+            old_address.set_bits(32..64, old_high as u64);
+            new_address.set_bits(32..64, new_high as u64);
+        }
+
+        Some(BarReprogramming {
+            bar_index: self.bar_index,
+            old_address,
+            new_address,
+            size: self.size,
+        })
+    }
+}
+
+/// PCI-to-PCI bridges have a Type-1 header, so the remainder of the header is of the form:
+/// ```ignore
+///     32                           16                              0
+///     +-----------------------------------------------------------+ 0x00
+///     |                                                           |
+///     |                Predefined region of header                |
+///     |                                                           |
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |                  Base Address Register 0                  | 0x10
+///     |                                                           |
+///     +-----------------------------------------------------------+
+///     |                  Base Address Register 1                  | 0x14
+///     |                                                           |
+///     +--------------+--------------+---------------+-------------+
+///     |   Secondary  |  Subordinate |   Secondary    |   Primary   | 0x18
+///     |    Latency   |  Bus Number  |  Bus Number    | Bus Number  |
+///     |     Timer    |              |                |             |
+///     +--------------+--------------+---------------+-------------+
+///     |       Secondary Status      |   I/O Limit    |  I/O Base   | 0x1c
+///     |                             |                |             |
+///     +-----------------------------+----------------+-------------+
+///     |         Memory Limit        |            Memory Base       | 0x20
+///     |                             |                               |
+///     +-----------------------------+-------------------------------+
+///     |  Prefetchable Memory Limit  |   Prefetchable Memory Base    | 0x24
+///     |                             |                               |
+///     +-----------------------------+-------------------------------+
+///     |               Prefetchable Base Upper 32 Bits                | 0x28
+///     |                                                               |
+///     +---------------------------------------------------------------+
+///     |              Prefetchable Limit Upper 32 Bits                | 0x2c
+///     |                                                               |
+///     +-----------------------------+-------------------------------+
+///     |    I/O Limit Upper 16 Bits   |   I/O Base Upper 16 Bits     | 0x30
+///     |                             |                               |
+///     +--------------------------------------------+--------------+
+///     |                 Reserved                   | Capabilities | 0x34
+///     |                                            |   Pointer    |
+///     +--------------------------------------------+--------------+
+///     |               Expansion ROM Base Address                  | 0x38
+///     |                                                           |
+///     +--------------+--------------+--------------+--------------+
+///     |        Bridge Control       |  Interrupt   |  Interrupt   | 0x3c
+///     |                             |     Pin      |     Line     |
+///     +--------------+--------------+--------------+--------------+
+/// ```
+pub struct PciBridgeHeader(PciAddress);
+
+impl PciBridgeHeader {
+    pub fn from_header(
+        header: PciHeader,
+        access: &impl ConfigRegionAccess,
+    ) -> Option<PciBridgeHeader> {
+        match header.header_type(access) {
+            0x01 => Some(PciBridgeHeader(header.0)),
+            _ => None,
+        }
+    }
+
+    pub fn header(&self) -> PciHeader {
+        PciHeader(self.0)
+    }
+
+    pub fn status(&self, access: &impl ConfigRegionAccess) -> StatusRegister {
+        let data = access.read(self.0, 0x4).get_bits(16..32);
+        StatusRegister::new(data as u16)
+    }
+
+    /// Get the contents of a BAR in a given slot. Empty bars will return `None`.
+    ///
+    /// ### Note
+    /// 64-bit memory BARs use two slots, so if one is decoded in e.g. slot #0, this method should not be called
+    /// for slot #1
+    pub fn bar(&self, slot: u8, access: &impl ConfigRegionAccess) -> Option<Bar> {
+        assert!(slot < 2, "PCI-to-PCI bridges only have two BAR slots");
+        EndpointHeader(self.0).bar(slot, access)
+    }
+
+    pub fn bus_numbers(&self, access: &impl ConfigRegionAccess) -> (u8, u8, u8, u8) {
+        let data = access.read(self.0, 0x18);
+        (
+            data.get_bits(0..8) as u8,
+            data.get_bits(8..16) as u8,
+            data.get_bits(16..24) as u8,
+            data.get_bits(24..32) as u8,
+        )
+    }
+
+    pub fn io_range(&self, access: &impl ConfigRegionAccess) -> (u32, u32) {
+        let data = access.read(self.0, 0x1c);
+        let base = data.get_bits(4..8) << 12;
+        let limit = (data.get_bits(12..16) << 12) | 0xfff;
+        (base, limit)
+    }
+
+    pub fn memory_range(&self, access: &impl ConfigRegionAccess) -> (u32, u32) {
+        let data = access.read(self.0, 0x20);
+        let base = data.get_bits(4..16) << 20;
+        let limit = (data.get_bits(20..32) << 20) | 0xf_ffff;
+        (base, limit)
+    }
+
+    pub fn prefetchable_memory_range(&self, access: &impl ConfigRegionAccess) -> (u64, u64) {
+        let data = access.read(self.0, 0x24);
+        let mut base = (data.get_bits(4..16) as u64) << 20;
+        let mut limit = ((data.get_bits(20..32) as u64) << 20) | 0xf_ffff;
+        base.set_bits(32..64, access.read(self.0, 0x28) as u64);
+        limit.set_bits(32..64, access.read(self.0, 0x2c) as u64);
+        (base, limit)
+    }
+
+    pub fn bridge_control(&self, access: &impl ConfigRegionAccess) -> u16 {
+        access.read(self.0, 0x3c).get_bits(16..32) as u16
+    }
+}
@@ -0,0 +1,177 @@
+use bit_field::BitField;
+
+/// Controls a function's ability to generate and respond to PCI cycles. This is the lower half
+/// of the dword at offset `0x04`, paired with the Status register in the upper half.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CommandRegister(u16);
+
+impl CommandRegister {
+    pub const IO_SPACE_ENABLE: u16 = 1 << 0;
+    pub const MEMORY_SPACE_ENABLE: u16 = 1 << 1;
+    pub const BUS_MASTER_ENABLE: u16 = 1 << 2;
+    pub const SPECIAL_CYCLE_ENABLE: u16 = 1 << 3;
+    pub const MEMORY_WRITE_AND_INVALIDATE_ENABLE: u16 = 1 << 4;
+    pub const VGA_PALETTE_SNOOP: u16 = 1 << 5;
+    pub const PARITY_ERROR_RESPONSE: u16 = 1 << 6;
+    pub const SERR_ENABLE: u16 = 1 << 8;
+    pub const FAST_BACK_TO_BACK_ENABLE: u16 = 1 << 9;
+    pub const INTERRUPT_DISABLE: u16 = 1 << 10;
+
+    pub(crate) fn new(value: u16) -> CommandRegister {
+        CommandRegister(value)
+    }
+
+    pub(crate) fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    pub fn io_space_enable(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn set_io_space_enable(&mut self, enabled: bool) {
+        self.0.set_bit(0, enabled);
+    }
+
+    pub fn memory_space_enable(&self) -> bool {
+        self.0.get_bit(1)
+    }
+
+    pub fn set_memory_space_enable(&mut self, enabled: bool) {
+        self.0.set_bit(1, enabled);
+    }
+
+    pub fn bus_master_enable(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    pub fn set_bus_master_enable(&mut self, enabled: bool) {
+        self.0.set_bit(2, enabled);
+    }
+
+    pub fn special_cycle_enable(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn set_special_cycle_enable(&mut self, enabled: bool) {
+        self.0.set_bit(3, enabled);
+    }
+
+    pub fn memory_write_and_invalidate_enable(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn set_memory_write_and_invalidate_enable(&mut self, enabled: bool) {
+        self.0.set_bit(4, enabled);
+    }
+
+    pub fn vga_palette_snoop(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn set_vga_palette_snoop(&mut self, enabled: bool) {
+        self.0.set_bit(5, enabled);
+    }
+
+    pub fn parity_error_response(&self) -> bool {
+        self.0.get_bit(6)
+    }
+
+    pub fn set_parity_error_response(&mut self, enabled: bool) {
+        self.0.set_bit(6, enabled);
+    }
+
+    pub fn serr_enable(&self) -> bool {
+        self.0.get_bit(8)
+    }
+
+    pub fn set_serr_enable(&mut self, enabled: bool) {
+        self.0.set_bit(8, enabled);
+    }
+
+    pub fn fast_back_to_back_enable(&self) -> bool {
+        self.0.get_bit(9)
+    }
+
+    pub fn set_fast_back_to_back_enable(&mut self, enabled: bool) {
+        self.0.set_bit(9, enabled);
+    }
+
+    pub fn interrupt_disable(&self) -> bool {
+        self.0.get_bit(10)
+    }
+
+    pub fn set_interrupt_disable(&mut self, enabled: bool) {
+        self.0.set_bit(10, enabled);
+    }
+}
+
+/// The timing of DEVSEL#, indicating the speed at which a device decodes its address and asserts
+/// `DEVSEL#` during a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DevselTiming {
+    Fast = 0x0,
+    Medium = 0x1,
+    Slow = 0x2,
+}
+
+/// Contains information about a PCI function's status, such as whether it has a capability list,
+/// and which interrupt/error conditions it's reporting. This is the upper half of the dword at
+/// offset `0x04`, paired with the Command register in the lower half.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StatusRegister(u16);
+
+impl StatusRegister {
+    pub(crate) fn new(value: u16) -> StatusRegister {
+        StatusRegister(value)
+    }
+
+    pub fn interrupt_status(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn has_capability_list(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn is_66mhz_capable(&self) -> bool {
+        self.0.get_bit(5)
+    }
+
+    pub fn can_do_fast_back_to_back_transactions(&self) -> bool {
+        self.0.get_bit(7)
+    }
+
+    pub fn master_data_parity_error(&self) -> bool {
+        self.0.get_bit(8)
+    }
+
+    pub fn devsel_timing(&self) -> DevselTiming {
+        match self.0.get_bits(9..11) {
+            0x0 => DevselTiming::Fast,
+            0x1 => DevselTiming::Medium,
+            0x2 => DevselTiming::Slow,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn signalled_target_abort(&self) -> bool {
+        self.0.get_bit(11)
+    }
+
+    pub fn received_target_abort(&self) -> bool {
+        self.0.get_bit(12)
+    }
+
+    pub fn received_master_abort(&self) -> bool {
+        self.0.get_bit(13)
+    }
+
+    pub fn signalled_system_error(&self) -> bool {
+        self.0.get_bit(14)
+    }
+
+    pub fn detected_parity_error(&self) -> bool {
+        self.0.get_bit(15)
+    }
+}